@@ -9,7 +9,7 @@ use bytes::BytesMut;
 use chrono::{DateTime, Utc};
 use futures_util::StreamExt;
 use indexmap::IndexMap;
-use reqwest::{Client as ReqwestClient, Method, RequestBuilder};
+use reqwest::{header::CONTENT_TYPE, Client as ReqwestClient, Method, RequestBuilder};
 use serde::Deserialize;
 use serde_json::{json, Value};
 
@@ -172,8 +172,7 @@ impl Client for BedrockClient {
 
 async fn chat_completions(builder: RequestBuilder) -> Result<ChatCompletionsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
 
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
@@ -190,10 +189,11 @@ async fn chat_completions_streaming(
     let res = builder.send().await?;
     let status = res.status();
     if !status.is_success() {
-        let data: Value = res.json().await?;
+        let (_, data) = read_json(res).await?;
         catch_error(&data, status.as_u16())?;
         bail!("Invalid response data: {data}");
     }
+    catch_unexpected_content(res.headers().get(CONTENT_TYPE), status.as_u16())?;
 
     let mut function_name = String::new();
     let mut function_arguments = String::new();
@@ -295,8 +295,7 @@ async fn chat_completions_streaming(
 
 async fn embeddings(builder: RequestBuilder) -> Result<EmbeddingsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
 
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
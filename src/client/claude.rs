@@ -65,8 +65,7 @@ pub async fn claude_chat_completions(
     _model: &Model,
 ) -> Result<ChatCompletionsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
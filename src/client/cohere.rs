@@ -110,8 +110,7 @@ async fn chat_completions(
     _model: &Model,
 ) -> Result<ChatCompletionsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
@@ -190,8 +189,7 @@ async fn chat_completions_streaming(
 
 async fn embeddings(builder: RequestBuilder, _model: &Model) -> Result<EmbeddingsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
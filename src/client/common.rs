@@ -13,7 +13,10 @@ use indexmap::IndexMap;
 use inquire::{
     list_option::ListOption, required, validator::Validation, MultiSelect, Select, Text,
 };
-use reqwest::{Client as ReqwestClient, RequestBuilder};
+use reqwest::{
+    header::{HeaderValue, CONTENT_TYPE},
+    Client as ReqwestClient, RequestBuilder, Response, StatusCode,
+};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::sync::LazyLock;
@@ -93,7 +96,7 @@ pub trait Client: Sync + Send {
                 }
                 let client = self.build_client()?;
                 let data = input.prepare_completion_data(self.model(), true)?;
-                self.chat_completions_streaming_inner(&client, handler, data).await
+                self.chat_completions_streaming_with_retry(&client, handler, data).await
             } => {
                 handler.done();
                 ret.with_context(|| "Failed to call chat-completions api")
@@ -105,6 +108,39 @@ pub trait Client: Sync + Send {
         }
     }
 
+    async fn chat_completions_streaming_with_retry(
+        &self,
+        client: &ReqwestClient,
+        handler: &mut SseHandler,
+        data: ChatCompletionsData,
+    ) -> Result<()> {
+        let abort_signal = handler.abort();
+        let max_retries = self.extra_config().and_then(|v| v.max_retries).unwrap_or(2);
+        let mut attempt = 0;
+        loop {
+            let ret = self
+                .chat_completions_streaming_inner(client, handler, data.clone())
+                .await;
+            match ret {
+                // Retrying after output has been emitted would duplicate it
+                Err(err)
+                    if attempt < max_retries
+                        && handler.is_empty()
+                        && !abort_signal.aborted()
+                        && is_transient_error(&err) =>
+                {
+                    attempt += 1;
+                    debug!("attempt {attempt} failed, retrying: {err}");
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::from_millis(500 * 2u64.pow(attempt - 1))) => {}
+                        _ = wait_abort_signal(&abort_signal) => return Err(err),
+                    }
+                }
+                ret => return ret,
+            }
+        }
+    }
+
     async fn embeddings(&self, data: &EmbeddingsData) -> Result<Vec<Vec<f32>>> {
         let client = self.build_client()?;
         self.embeddings_inner(&client, data)
@@ -201,6 +237,7 @@ impl Default for ClientConfig {
 pub struct ExtraConfig {
     pub proxy: Option<String>,
     pub connect_timeout: Option<u64>,
+    pub max_retries: Option<u32>,
 }
 
 #[derive(Debug, Clone, Deserialize, Default)]
@@ -277,7 +314,7 @@ impl RequestData {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ChatCompletionsData {
     pub messages: Vec<Message>,
     pub temperature: Option<f64>,
@@ -495,41 +532,129 @@ pub fn catch_error(data: &Value, status: u16) -> Result<()> {
         return Ok(());
     }
     debug!("Invalid response, status: {status}, data: {data}");
+    let message = extract_error_message(data)
+        .unwrap_or_else(|| format!("Invalid response data: {data} (status: {status})"));
+    Err(ResponseError { status, message }.into())
+}
+
+fn extract_error_message(data: &Value) -> Option<String> {
     if let Some(error) = data["error"].as_object() {
         if let (Some(typ), Some(message)) = (
             json_str_from_map(error, "type"),
             json_str_from_map(error, "message"),
         ) {
-            bail!("{message} (type: {typ})");
+            return Some(format!("{message} (type: {typ})"));
         } else if let (Some(typ), Some(message)) = (
             json_str_from_map(error, "code"),
             json_str_from_map(error, "message"),
         ) {
-            bail!("{message} (code: {typ})");
+            return Some(format!("{message} (code: {typ})"));
         }
     } else if let Some(error) = data["errors"][0].as_object() {
         if let (Some(code), Some(message)) = (
             error.get("code").and_then(|v| v.as_u64()),
             json_str_from_map(error, "message"),
         ) {
-            bail!("{message} (status: {code})")
+            return Some(format!("{message} (status: {code})"));
         }
     } else if let Some(error) = data[0]["error"].as_object() {
         if let (Some(status), Some(message)) = (
             json_str_from_map(error, "status"),
             json_str_from_map(error, "message"),
         ) {
-            bail!("{message} (status: {status})")
+            return Some(format!("{message} (status: {status})"));
         }
     } else if let (Some(detail), Some(status)) = (data["detail"].as_str(), data["status"].as_i64())
     {
-        bail!("{detail} (status: {status})");
+        return Some(format!("{detail} (status: {status})"));
     } else if let Some(error) = data["error"].as_str() {
-        bail!("{error}");
+        return Some(error.to_string());
     } else if let Some(message) = data["message"].as_str() {
-        bail!("{message}");
+        return Some(message.to_string());
+    }
+    None
+}
+
+/// An error response from the api, kept typed so retries can tell transient failures apart
+#[derive(Debug)]
+pub struct ResponseError {
+    pub status: u16,
+    pub message: String,
+}
+
+impl ResponseError {
+    pub fn invalid_data(text: &str, status: u16) -> Self {
+        Self {
+            status,
+            message: format!(
+                "Invalid response data: {} (status: {status})",
+                truncate_response_text(text)
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for ResponseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ResponseError {}
+
+pub fn is_transient_error(err: &anyhow::Error) -> bool {
+    err.chain().any(|err| {
+        if let Some(err) = err.downcast_ref::<reqwest::Error>() {
+            err.is_connect() || err.is_timeout() || err.is_request()
+        } else if let Some(err) = err.downcast_ref::<ResponseError>() {
+            err.status == 429 || err.status >= 500
+        } else {
+            false
+        }
+    })
+}
+
+pub async fn read_json(res: Response) -> Result<(StatusCode, Value)> {
+    let status = res.status();
+    let content_type = res.headers().get(CONTENT_TYPE).cloned();
+    let text = res.text().await?;
+    let data: Value = match text.parse() {
+        Ok(data) => data,
+        Err(_) => {
+            catch_unexpected_content(content_type.as_ref(), status.as_u16())?;
+            return Err(ResponseError::invalid_data(&text, status.as_u16()).into());
+        }
+    };
+    Ok((status, data))
+}
+
+pub fn catch_unexpected_content(content_type: Option<&HeaderValue>, status: u16) -> Result<()> {
+    let content_type = content_type
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let content_type = content_type.split(';').next().unwrap_or_default().trim();
+    let is_expected = content_type.is_empty()
+        || content_type.contains("json")
+        || content_type.contains("event-stream")
+        || content_type == "application/vnd.amazon.eventstream";
+    // A login or landing page points at a wrong api_base; other error pages such as
+    // gateway timeouts carry the provider's own message, so leave them to the caller
+    let is_landing_page =
+        content_type == "text/html" && ((200..400).contains(&status) || status == 404);
+    if is_landing_page || ((200..300).contains(&status) && !is_expected) {
+        bail!("Unexpected response with content-type '{content_type}' (status: {status}), please check the api_base of the client");
+    }
+    Ok(())
+}
+
+pub fn truncate_response_text(text: &str) -> String {
+    const MAX_CHARS: usize = 500;
+    let text = text.trim();
+    match text.char_indices().nth(MAX_CHARS) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text.to_string(),
     }
-    bail!("Invalid response data: {data} (status: {status})");
 }
 
 pub fn json_str_from_map<'a>(
@@ -674,3 +799,214 @@ fn prompt_input_string(
     let text = text.prompt()?;
     Ok(text)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_response(content_type: &str, status: u16, body: &str) -> Response {
+        http::Response::builder()
+            .status(status)
+            .header(CONTENT_TYPE, content_type)
+            .body(body.to_string())
+            .unwrap()
+            .into()
+    }
+
+    #[tokio::test]
+    async fn test_read_json() {
+        let res = build_response(
+            "text/html; charset=utf-8",
+            200,
+            "<html><body>Sign in</body></html>",
+        );
+        let err = read_json(res).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected response with content-type 'text/html' (status: 200), please check the api_base of the client"
+        );
+
+        let res = build_response("text/plain", 502, "upstream connect error");
+        let err = read_json(res).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid response data: upstream connect error (status: 502)"
+        );
+
+        let res = build_response("text/plain", 401, r#"{"error":"Unauthorized"}"#);
+        let (status, data) = read_json(res).await.unwrap();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(catch_error(&data, status.as_u16())
+            .unwrap_err()
+            .to_string()
+            .contains("Unauthorized"));
+
+        let res = build_response("text/plain", 200, "hello");
+        assert!(read_json(res).await.is_err());
+
+        let res = build_response("text/plain", 200, r#"{"id":"1"}"#);
+        let (_, data) = read_json(res).await.unwrap();
+        assert_eq!(data, json!({ "id": "1" }));
+
+        let res = build_response("text/html", 503, "<html>Service Unavailable</html>");
+        let err = read_json(res).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid response data: <html>Service Unavailable</html> (status: 503)"
+        );
+
+        let res = build_response("Application/JSON", 200, r#"{"id":"1"}"#);
+        assert!(read_json(res).await.is_ok());
+
+        let res = build_response("application/json", 200, r#"{"id":"1"}"#);
+        let (status, data) = read_json(res).await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(data, json!({ "id": "1" }));
+    }
+
+    #[test]
+    fn test_catch_unexpected_content() {
+        let check = |content_type: &str, status: u16| {
+            catch_unexpected_content(Some(&HeaderValue::from_str(content_type).unwrap()), status)
+        };
+        assert!(check("text/html", 302).is_err());
+        assert!(check("text/html", 404).is_err());
+        assert!(check("text/html", 401).is_ok());
+        assert!(check("text/html", 502).is_ok());
+        assert!(check("text/plain", 401).is_ok());
+        assert!(check("application/xml", 503).is_ok());
+        assert!(check("text/plain", 200).is_err());
+        assert!(check("text/event-stream", 200).is_ok());
+        assert!(check("application/vnd.amazon.eventstream", 200).is_ok());
+        assert!(check("Application/JSON; charset=UTF-8", 200).is_ok());
+        assert!(catch_unexpected_content(None, 200).is_ok());
+        assert!(!is_transient_error(&check("text/html", 200).unwrap_err()));
+    }
+
+    #[test]
+    fn test_truncate_response_text() {
+        assert_eq!(truncate_response_text(" Unauthorized\n"), "Unauthorized");
+        let text = "é".repeat(600);
+        assert_eq!(
+            truncate_response_text(&text),
+            format!("{}...", "é".repeat(500))
+        );
+    }
+
+    struct MockClient {
+        global_config: GlobalConfig,
+        model: Model,
+        failures: std::sync::atomic::AtomicUsize,
+        attempts: std::sync::atomic::AtomicUsize,
+        status: u16,
+        emit_before_failure: bool,
+    }
+
+    impl MockClient {
+        fn new(failures: usize, status: u16, emit_before_failure: bool) -> Self {
+            Self {
+                global_config: std::sync::Arc::new(parking_lot::RwLock::new(Config::default())),
+                model: Model::new("mock", "mock"),
+                failures: failures.into(),
+                attempts: 0.into(),
+                status,
+                emit_before_failure,
+            }
+        }
+
+        fn attempts(&self) -> usize {
+            self.attempts.load(std::sync::atomic::Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl Client for MockClient {
+        fn global_config(&self) -> &GlobalConfig {
+            &self.global_config
+        }
+
+        fn extra_config(&self) -> Option<&ExtraConfig> {
+            None
+        }
+
+        fn patch_config(&self) -> Option<&RequestPatch> {
+            None
+        }
+
+        fn name(&self) -> &str {
+            "mock"
+        }
+
+        fn model(&self) -> &Model {
+            &self.model
+        }
+
+        fn model_mut(&mut self) -> &mut Model {
+            &mut self.model
+        }
+
+        async fn chat_completions_inner(
+            &self,
+            _client: &ReqwestClient,
+            _data: ChatCompletionsData,
+        ) -> Result<ChatCompletionsOutput> {
+            unreachable!()
+        }
+
+        async fn chat_completions_streaming_inner(
+            &self,
+            _client: &ReqwestClient,
+            handler: &mut SseHandler,
+            _data: ChatCompletionsData,
+        ) -> Result<()> {
+            use std::sync::atomic::Ordering;
+            self.attempts.fetch_add(1, Ordering::SeqCst);
+            if self.failures.load(Ordering::SeqCst) > 0 {
+                self.failures.fetch_sub(1, Ordering::SeqCst);
+                if self.emit_before_failure {
+                    handler.text("Hello")?;
+                }
+                catch_error(&json!({ "error": "upstream failure" }), self.status)?;
+            }
+            handler.text("Hello")?;
+            handler.text(" world")?;
+            Ok(())
+        }
+    }
+
+    async fn run_streaming(client: &MockClient) -> (Result<()>, String) {
+        let input = Input::from_str(&client.global_config, "hi", None);
+        let (tx, _rx) = unbounded_channel();
+        let mut handler = SseHandler::new(tx, create_abort_signal());
+        let ret = client
+            .chat_completions_streaming(&input, &mut handler)
+            .await;
+        (ret, handler.take().0)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_chat_completions_streaming_retry() {
+        let client = MockClient::new(1, 503, false);
+        let (ret, text) = run_streaming(&client).await;
+        assert!(ret.is_ok());
+        assert_eq!(text, "Hello world");
+        assert_eq!(client.attempts(), 2);
+
+        let client = MockClient::new(3, 429, false);
+        let (ret, text) = run_streaming(&client).await;
+        assert!(ret.is_err());
+        assert_eq!(text, "");
+        assert_eq!(client.attempts(), 3);
+
+        let client = MockClient::new(1, 401, false);
+        let (ret, _) = run_streaming(&client).await;
+        assert!(ret.is_err());
+        assert_eq!(client.attempts(), 1);
+
+        let client = MockClient::new(1, 503, true);
+        let (ret, text) = run_streaming(&client).await;
+        assert!(ret.is_err());
+        assert_eq!(text, "Hello");
+        assert_eq!(client.attempts(), 1);
+    }
+}
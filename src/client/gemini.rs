@@ -4,7 +4,7 @@ use super::*;
 use anyhow::{Context, Result};
 use reqwest::RequestBuilder;
 use serde::Deserialize;
-use serde_json::{json, Value};
+use serde_json::json;
 
 const API_BASE: &str = "https://generativelanguage.googleapis.com/v1beta";
 
@@ -109,8 +109,7 @@ fn prepare_embeddings(self_: &GeminiClient, data: &EmbeddingsData) -> Result<Req
 
 async fn embeddings(builder: RequestBuilder, _model: &Model) -> Result<EmbeddingsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
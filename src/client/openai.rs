@@ -87,8 +87,7 @@ pub async fn openai_chat_completions(
     _model: &Model,
 ) -> Result<ChatCompletionsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
@@ -202,8 +201,7 @@ pub async fn openai_embeddings(
     _model: &Model,
 ) -> Result<EmbeddingsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
@@ -1,9 +1,11 @@
-use super::{catch_error, ToolCall};
+use super::{
+    catch_error, catch_unexpected_content, truncate_response_text, ResponseError, ToolCall,
+};
 use crate::utils::AbortSignal;
 
 use anyhow::{anyhow, bail, Context, Result};
 use futures_util::{Stream, StreamExt};
-use reqwest::RequestBuilder;
+use reqwest::{header::CONTENT_TYPE, RequestBuilder};
 use reqwest_eventsource::{Error as EventSourceError, Event, RequestBuilderExt};
 use serde_json::Value;
 use tokio::sync::mpsc::UnboundedSender;
@@ -65,6 +67,10 @@ impl SseHandler {
         self.abort_signal.clone()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.buffer.is_empty() && self.tool_calls.is_empty()
+    }
+
     pub fn tool_calls(&self) -> &[ToolCall] {
         &self.tool_calls
     }
@@ -111,25 +117,29 @@ where
                 match err {
                     EventSourceError::StreamEnded => {}
                     EventSourceError::InvalidStatusCode(status, res) => {
+                        let content_type = res.headers().get(CONTENT_TYPE).cloned();
                         let text = res.text().await?;
                         let data: Value = match text.parse() {
                             Ok(data) => data,
                             Err(_) => {
-                                bail!(
-                                    "Invalid response data: {text} (status: {})",
-                                    status.as_u16()
+                                catch_unexpected_content(content_type.as_ref(), status.as_u16())?;
+                                return Err(
+                                    ResponseError::invalid_data(&text, status.as_u16()).into()
                                 );
                             }
                         };
                         catch_error(&data, status.as_u16())?;
                     }
                     EventSourceError::InvalidContentType(header_value, res) => {
+                        catch_unexpected_content(Some(&header_value), res.status().as_u16())?;
                         let text = res.text().await?;
                         bail!(
-                            "Invalid response event-stream. content-type: {}, data: {text}",
-                            header_value.to_str().unwrap_or_default()
+                            "Invalid response event-stream. content-type: {}, data: {}",
+                            header_value.to_str().unwrap_or_default(),
+                            truncate_response_text(&text)
                         );
                     }
+                    EventSourceError::Transport(err) => return Err(err.into()),
                     _ => {
                         bail!("{}", err);
                     }
@@ -212,10 +222,8 @@ impl JsonStreamParser {
                     }
                     self.balances.push(ch);
                 }
-                '[' => {
-                    if self.start.is_some() {
-                        self.balances.push(ch);
-                    }
+                '[' if self.start.is_some() => {
+                    self.balances.push(ch);
                 }
                 '}' => {
                     self.balances.pop();
@@ -293,4 +301,42 @@ mod tests {
 {"key": "value3"}"#;
         assert_json_stream!(input, output);
     }
+
+    fn serve_once(status: &str, content_type: &str, body: &str) -> String {
+        use std::io::{Read, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let response = format!(
+            "HTTP/1.1 {status}\r\ncontent-type: {content_type}\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0; 4096];
+            let _ = stream.read(&mut buf);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn test_sse_stream_unexpected_content() {
+        let url = serve_once("200 OK", "text/html", "<html><body>Sign in</body></html>");
+        let err = sse_stream(reqwest::Client::new().get(url), |_| Ok(false))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unexpected response with content-type 'text/html' (status: 200), please check the api_base of the client"
+        );
+
+        let url = serve_once("401 Unauthorized", "text/plain", "Unauthorized");
+        let err = sse_stream(reqwest::Client::new().get(url), |_| Ok(false))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid response data: Unauthorized (status: 401)"
+        );
+    }
 }
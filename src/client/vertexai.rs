@@ -5,7 +5,7 @@ use super::*;
 
 use anyhow::{anyhow, bail, Context, Result};
 use chrono::{Duration, Utc};
-use reqwest::{Client as ReqwestClient, RequestBuilder};
+use reqwest::{header::CONTENT_TYPE, Client as ReqwestClient, RequestBuilder};
 use serde::Deserialize;
 use serde_json::{json, Value};
 use std::{path::PathBuf, str::FromStr};
@@ -177,8 +177,7 @@ pub async fn gemini_chat_completions(
     _model: &Model,
 ) -> Result<ChatCompletionsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
@@ -194,7 +193,7 @@ pub async fn gemini_chat_completions_streaming(
     let res = builder.send().await?;
     let status = res.status();
     if !status.is_success() {
-        let data: Value = res.json().await?;
+        let (_, data) = read_json(res).await?;
         catch_error(&data, status.as_u16())?;
     } else {
         let handle = |value: &str| -> Result<()> {
@@ -223,6 +222,7 @@ pub async fn gemini_chat_completions_streaming(
 
             Ok(())
         };
+        catch_unexpected_content(res.headers().get(CONTENT_TYPE), status.as_u16())?;
         json_stream(res.bytes_stream(), handle).await?;
     }
     Ok(())
@@ -230,8 +230,7 @@ pub async fn gemini_chat_completions_streaming(
 
 async fn embeddings(builder: RequestBuilder, _model: &Model) -> Result<EmbeddingsOutput> {
     let res = builder.send().await?;
-    let status = res.status();
-    let data: Value = res.json().await?;
+    let (status, data) = read_json(res).await?;
     if !status.is_success() {
         catch_error(&data, status.as_u16())?;
     }
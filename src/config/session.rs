@@ -10,8 +10,9 @@ use inquire::{validator::Validation, Confirm, Text};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::collections::HashMap;
-use std::fs::{read_to_string, write};
-use std::path::Path;
+use std::fs::{metadata, read_to_string, remove_file, rename, File};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
 static RE_AUTONAME_PREFIX: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\d{8}T\d{6}-").unwrap());
@@ -428,7 +429,33 @@ impl Session {
 
         let content = serde_yaml::to_string(&self)
             .with_context(|| format!("Failed to serde session '{}'", self.name))?;
-        write(session_path, content).with_context(|| {
+        // Resolve a symlinked session file so the rename replaces its target, not the link
+        let target_path = session_path
+            .canonicalize()
+            .unwrap_or_else(|_| session_path.to_path_buf());
+        let permissions = metadata(&target_path).ok().map(|v| v.permissions());
+        // Write to a synced sibling file first so a crash mid-write can't leave a truncated session,
+        // unique per save so two processes saving the same session can't interleave their writes
+        let mut tmp_path = target_path.clone().into_os_string();
+        tmp_path.push(format!(
+            ".{}-{}.tmp",
+            std::process::id(),
+            uuid::Uuid::new_v4()
+        ));
+        let tmp_path = PathBuf::from(tmp_path);
+        let ret = File::create_new(&tmp_path)
+            .and_then(|mut file| {
+                if let Some(permissions) = permissions {
+                    file.set_permissions(permissions)?;
+                }
+                file.write_all(content.as_bytes())?;
+                file.sync_all()
+            })
+            .and_then(|_| rename(&tmp_path, &target_path));
+        if ret.is_err() {
+            let _ = remove_file(&tmp_path);
+        }
+        ret.with_context(|| {
             format!(
                 "Failed to write session '{}' to '{}'",
                 self.name,
@@ -627,3 +654,98 @@ impl AutoName {
         !self.naming && self.chat_history.is_some() && self.name.is_none()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("aichat-test-{}", uuid::Uuid::new_v4()));
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn test_config() -> Config {
+        Config {
+            clients: vec![serde_json::from_value(json!({ "type": "openai" })).unwrap()],
+            ..Default::default()
+        }
+    }
+
+    fn test_session() -> Session {
+        Session {
+            model_id: "openai:gpt-4o".into(),
+            messages: vec![Message::new(
+                MessageRole::User,
+                MessageContent::Text("hello".into()),
+            )],
+            ..Default::default()
+        }
+    }
+
+    fn tmp_file_names(dir: &Path) -> Vec<String> {
+        std::fs::read_dir(dir)
+            .unwrap()
+            .filter_map(|v| v.ok())
+            .map(|v| v.file_name().to_string_lossy().to_string())
+            .filter(|v| v.ends_with(".tmp"))
+            .collect()
+    }
+
+    #[test]
+    fn test_save_ignores_stale_tmp_file() {
+        let dir = TempDir::new();
+        let session_path = dir.0.join("test.yaml");
+        std::fs::write(dir.0.join("test.yaml.tmp"), "stale").unwrap();
+
+        test_session().save("test", &session_path, false).unwrap();
+        test_session().save("test", &session_path, false).unwrap();
+
+        let loaded = Session::load(&test_config(), "test", &session_path).unwrap();
+        assert_eq!(loaded.messages.len(), 1);
+        assert_eq!(tmp_file_names(&dir.0), vec!["test.yaml.tmp".to_string()]);
+        assert_eq!(list_file_names(&dir.0, ".yaml"), vec!["test".to_string()]);
+    }
+
+    #[test]
+    fn test_save_removes_tmp_file_on_failure() {
+        let dir = TempDir::new();
+        let session_path = dir.0.join("test.yaml");
+        std::fs::create_dir_all(session_path.join("child")).unwrap();
+
+        assert!(test_session().save("test", &session_path, false).is_err());
+        assert!(tmp_file_names(&dir.0).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_save_follows_symlink() {
+        use std::os::unix::fs::{symlink, PermissionsExt};
+
+        let dir = TempDir::new();
+        let target_path = dir.0.join("dotfiles").join("test.yaml");
+        std::fs::create_dir_all(target_path.parent().unwrap()).unwrap();
+        std::fs::write(&target_path, "").unwrap();
+        std::fs::set_permissions(&target_path, std::fs::Permissions::from_mode(0o600)).unwrap();
+        let session_path = dir.0.join("test.yaml");
+        symlink(&target_path, &session_path).unwrap();
+
+        test_session().save("test", &session_path, false).unwrap();
+
+        assert!(session_path.symlink_metadata().unwrap().is_symlink());
+        let loaded = Session::load(&test_config(), "test", &target_path).unwrap();
+        assert_eq!(loaded.messages.len(), 1);
+        let mode = metadata(&target_path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+}
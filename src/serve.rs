@@ -16,7 +16,7 @@ use serde::Deserialize;
 use serde_json::{json, Value};
 use std::{
     convert::Infallible,
-    net::IpAddr,
+    net::{IpAddr, SocketAddr},
     sync::{
         atomic::{AtomicBool, Ordering},
         Arc,
@@ -40,15 +40,7 @@ type AppResponse = Response<BoxBody<Bytes, Infallible>>;
 
 pub async fn run(config: GlobalConfig, addr: Option<String>) -> Result<()> {
     let addr = match addr {
-        Some(addr) => {
-            if let Ok(port) = addr.parse::<u16>() {
-                format!("127.0.0.1:{port}")
-            } else if let Ok(ip) = addr.parse::<IpAddr>() {
-                format!("{ip}:8000")
-            } else {
-                addr
-            }
-        }
+        Some(addr) => parse_listen_addr(&addr)?,
         None => config.read().serve_addr(),
     };
     let server = Arc::new(Server::new(&config));
@@ -377,7 +369,7 @@ impl Server {
                         };
                     } else {
                         let ret = client
-                            .chat_completions_streaming_inner(http_client, handler, data)
+                            .chat_completions_streaming_with_retry(http_client, handler, data)
                             .await;
                         let first = match ret {
                             Ok(()) => None,
@@ -629,6 +621,24 @@ async fn shutdown_signal() {
         .expect("Failed to install CTRL+C signal handler")
 }
 
+fn parse_listen_addr(addr: &str) -> Result<String> {
+    if let Ok(port) = addr.parse::<u16>() {
+        return Ok(format!("127.0.0.1:{port}"));
+    }
+    if let Ok(ip) = addr.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, 8000).to_string());
+    }
+    if addr.parse::<SocketAddr>().is_ok() {
+        return Ok(addr.to_string());
+    }
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+            Ok(addr.to_string())
+        }
+        _ => bail!("Invalid serve address '{addr}', expected <port>, <ip> or <host>:<port>"),
+    }
+}
+
 fn generate_completion_id() -> String {
     let random_id = chrono::Utc::now().nanosecond();
     format!("chatcmpl-{random_id}")
@@ -882,7 +892,7 @@ fn parse_messages(message: Vec<Value>) -> Result<Vec<Message>> {
                     if tool_calls.len() == tool_values.len() {
                         let mut list = vec![];
                         for ((id, name, arguments), (value, tool_call_id)) in
-                            tool_calls.into_iter().zip(tool_values.into_iter())
+                            tool_calls.into_iter().zip(tool_values)
                         {
                             if id != tool_call_id {
                                 return Err(err());
@@ -933,3 +943,24 @@ fn parse_tools(tools: Option<Vec<Value>>) -> Result<Option<Vec<FunctionDeclarati
     }
     Ok(Some(functions))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listen_addr() {
+        assert_eq!(parse_listen_addr("8080").unwrap(), "127.0.0.1:8080");
+        assert_eq!(parse_listen_addr("0.0.0.0").unwrap(), "0.0.0.0:8000");
+        assert_eq!(parse_listen_addr("::1").unwrap(), "[::1]:8000");
+        assert_eq!(parse_listen_addr("0.0.0.0:8080").unwrap(), "0.0.0.0:8080");
+        assert_eq!(parse_listen_addr("[::]:8080").unwrap(), "[::]:8080");
+        assert_eq!(
+            parse_listen_addr("localhost:3000").unwrap(),
+            "localhost:3000"
+        );
+        assert!(parse_listen_addr("localhost").is_err());
+        assert!(parse_listen_addr("0.0.0.0:99999").is_err());
+        assert!(parse_listen_addr(":8080").is_err());
+    }
+}